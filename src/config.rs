@@ -0,0 +1,68 @@
+use clap::Parser;
+
+use crate::image_processor::service::ServiceDefaults;
+
+/// 服务启动配置，命令行参数优先，其次回退到对应的环境变量
+#[derive(Debug, Clone, Parser)]
+#[command(author, version, about = "图像处理服务")]
+pub struct AppConfig {
+    /// 监听地址
+    #[arg(long, env = "DRINKUP_HOST", default_value = "0.0.0.0")]
+    pub host: String,
+
+    /// 监听端口
+    #[arg(long, env = "DRINKUP_PORT", default_value_t = 3000)]
+    pub port: u16,
+
+    /// 并发处理请求数上限
+    #[arg(long, env = "DRINKUP_MAX_CONCURRENCY", default_value_t = 16)]
+    pub max_concurrency: usize,
+
+    /// 解码前允许的最大输入字节数
+    #[arg(long, env = "DRINKUP_MAX_INPUT_BYTES", default_value_t = 50 * 1024 * 1024)]
+    pub max_input_bytes: u64,
+
+    /// 解码时允许的最大图片宽度（像素）
+    #[arg(long, env = "DRINKUP_MAX_WIDTH", default_value_t = 10_000)]
+    pub max_width: u32,
+
+    /// 解码时允许的最大图片高度（像素）
+    #[arg(long, env = "DRINKUP_MAX_HEIGHT", default_value_t = 10_000)]
+    pub max_height: u32,
+
+    /// 解码时允许分配的最大内存字节数
+    #[arg(long, env = "DRINKUP_MAX_ALLOC_BYTES", default_value_t = 256 * 1024 * 1024)]
+    pub max_alloc_bytes: u64,
+
+    /// 背景移除的默认色彩容差
+    #[arg(long, env = "DRINKUP_DEFAULT_COLOR_TOLERANCE", default_value_t = 30.0)]
+    pub default_color_tolerance: f32,
+
+    /// 背景移除的默认边缘模糊程度
+    #[arg(long, env = "DRINKUP_DEFAULT_EDGE_BLUR", default_value_t = 2)]
+    pub default_edge_blur: u32,
+
+    /// 背景移除蒙版形态学开闭运算的默认迭代次数
+    #[arg(long, env = "DRINKUP_DEFAULT_MORPHOLOGY_ITERATIONS", default_value_t = 1)]
+    pub default_morphology_iterations: u32,
+}
+
+impl AppConfig {
+    /// 监听地址的"host:port"形式
+    pub fn listen_addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// 转换为`ImageProcessService`的默认参数
+    pub fn service_defaults(&self) -> ServiceDefaults {
+        ServiceDefaults {
+            max_input_bytes: self.max_input_bytes,
+            max_width: self.max_width,
+            max_height: self.max_height,
+            max_alloc_bytes: self.max_alloc_bytes,
+            default_color_tolerance: self.default_color_tolerance,
+            default_edge_blur: self.default_edge_blur,
+            default_morphology_iterations: self.default_morphology_iterations,
+        }
+    }
+}
@@ -1,79 +1,223 @@
-use axum::{extract::Multipart, Json};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Multipart, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose, Engine as _};
 use tracing::info;
 
 use crate::image_processor::{
     model::{AppError, CommonResp, ProcessImageData, ProcessImageRequest},
-    service::ImageProcessService,
+    service::{
+        parse_background, parse_filter_type, ImageProcessService, ProcessOptions, ResizeOptions,
+        ServiceDefaults, MAX_EDGE_BLUR, MAX_MORPHOLOGY_ITERATIONS,
+    },
 };
 
-/// 图片处理控制器
+/// 图片处理控制器，持有共享的服务实例（含结果缓存），通过axum State注入各路由
+#[derive(Clone)]
 pub struct ImageController {
-    service: ImageProcessService,
+    service: Arc<ImageProcessService>,
+}
+
+/// 根据image_url/image_data二选一获取原始图片字节。
+/// JSON和原始字节两个入口共享这一步，避免获取/校验逻辑两处漂移
+async fn resolve_image_data(
+    service: &ImageProcessService,
+    image_url: Option<&str>,
+    image_data: Option<&str>,
+) -> Result<Vec<u8>, AppError> {
+    match (image_url, image_data) {
+        (Some(url), None) => service.download_image(url).await,
+        (None, Some(data)) => service.decode_base64_image(data),
+        (Some(_), Some(_)) => Err(AppError::InvalidInput(
+            "请只提供image_url或image_data中的一个".to_string(),
+        )),
+        (None, None) => Err(AppError::InvalidInput(
+            "请提供image_url或image_data".to_string(),
+        )),
+    }
+}
+
+/// 解析缩放参数，三个处理入口共享这一步，避免参数拼装逻辑三处漂移
+fn build_resize_options(
+    width: Option<u32>,
+    height: Option<u32>,
+    resize_filter: Option<&str>,
+    preserve_aspect: bool,
+) -> ResizeOptions {
+    ResizeOptions {
+        width,
+        height,
+        filter: resize_filter.map(parse_filter_type),
+        preserve_aspect,
+    }
+}
+
+/// 汇总背景/去背参数，套用服务默认值后构建本次请求生效的处理参数及输出格式。
+/// 三个处理入口（JSON/原始字节/表单）共享这一步，避免参数拼装逻辑三处漂移
+fn build_options(
+    service: &ImageProcessService,
+    resize: ResizeOptions,
+    background_spec: Option<&str>,
+    output_format: Option<&str>,
+    color_tolerance: Option<f32>,
+    edge_blur: Option<u32>,
+    morphology_iterations: Option<u32>,
+) -> Result<(ProcessOptions, String), AppError> {
+    let background = background_spec
+        .map(parse_background)
+        .transpose()?
+        .unwrap_or_default();
+
+    // 确定输出格式：合成了不透明背景时默认改用JPEG，否则默认PNG
+    let output_format = output_format.map(str::to_lowercase).unwrap_or_else(|| {
+        if background.is_opaque() {
+            "jpg".to_string()
+        } else {
+            "png".to_string()
+        }
+    });
+
+    let options = ProcessOptions {
+        resize,
+        background,
+        color_tolerance: color_tolerance.unwrap_or_else(|| service.default_color_tolerance()),
+        edge_blur: edge_blur
+            .unwrap_or_else(|| service.default_edge_blur())
+            .min(MAX_EDGE_BLUR),
+        morphology_iterations: morphology_iterations
+            .unwrap_or_else(|| service.default_morphology_iterations())
+            .min(MAX_MORPHOLOGY_ITERATIONS),
+    };
+
+    Ok((options, output_format))
 }
 
 impl ImageController {
-    pub fn new() -> Self {
+    /// 使用启动时配置的默认值创建控制器
+    pub fn with_defaults(defaults: ServiceDefaults) -> Self {
         Self {
-            service: ImageProcessService::new(),
+            service: Arc::new(ImageProcessService::with_defaults(defaults)),
         }
     }
 
     /// 处理图像（JSON格式）
     pub async fn process_image(
+        State(controller): State<ImageController>,
         Json(request): Json<ProcessImageRequest>,
     ) -> Result<Json<CommonResp<ProcessImageData>>, AppError> {
         info!("收到图像处理请求");
 
-        let controller = Self::new();
-
         // 获取图像数据
-        let image_data = match (&request.image_url, &request.image_data) {
-            (Some(url), None) => controller.service.download_image(url).await?,
-            (None, Some(data)) => controller.service.decode_base64_image(data)?,
-            (Some(_), Some(_)) => {
-                return Err(AppError::InvalidInput(
-                    "请只提供image_url或image_data中的一个".to_string(),
-                ));
-            }
-            (None, None) => {
-                return Err(AppError::InvalidInput(
-                    "请提供image_url或image_data".to_string(),
-                ));
-            }
-        };
+        let image_data = resolve_image_data(
+            &controller.service,
+            request.image_url.as_deref(),
+            request.image_data.as_deref(),
+        )
+        .await?;
 
-        // 处理图像
-        let processed_image = controller.service.process_image_data(image_data).await?;
+        // 解析缩放参数
+        let resize = build_resize_options(
+            request.width,
+            request.height,
+            request.resize_filter.as_deref(),
+            request.preserve_aspect.unwrap_or(false),
+        );
 
-        // 确定输出格式
-        let output_format = request
-            .output_format
-            .as_deref()
-            .unwrap_or("png")
-            .to_lowercase();
+        let (options, output_format) = build_options(
+            &controller.service,
+            resize,
+            request.background.as_deref(),
+            request.output_format.as_deref(),
+            request.color_tolerance,
+            request.edge_blur,
+            request.morphology_iterations,
+        )?;
 
-        // 转换为Base64
-        let base64_image = controller
+        // 处理图像并编码（命中缓存时跳过解码/去背）
+        let (bytes, _mime) = controller
             .service
-            .image_to_base64(processed_image, &output_format)?;
+            .process_and_encode(image_data, options, output_format.clone())
+            .await?;
 
         let response_data = ProcessImageData {
-            processed_image: base64_image,
+            processed_image: general_purpose::STANDARD.encode(&bytes),
             format: output_format,
         };
 
         Ok(Json(CommonResp::success(response_data)))
     }
 
+    /// 处理图像并直接返回原始字节（无Base64/JSON封装）
+    pub async fn process_image_raw(
+        State(controller): State<ImageController>,
+        Json(request): Json<ProcessImageRequest>,
+    ) -> Result<Response, AppError> {
+        info!("收到二进制图像处理请求");
+
+        // 获取图像数据
+        let image_data = resolve_image_data(
+            &controller.service,
+            request.image_url.as_deref(),
+            request.image_data.as_deref(),
+        )
+        .await?;
+
+        // 解析缩放参数
+        let resize = build_resize_options(
+            request.width,
+            request.height,
+            request.resize_filter.as_deref(),
+            request.preserve_aspect.unwrap_or(false),
+        );
+
+        let (options, output_format) = build_options(
+            &controller.service,
+            resize,
+            request.background.as_deref(),
+            request.output_format.as_deref(),
+            request.color_tolerance,
+            request.edge_blur,
+            request.morphology_iterations,
+        )?;
+
+        // 处理图像并编码，直接以对应Content-Type返回原始字节
+        let (bytes, mime) = controller
+            .service
+            .process_and_encode(image_data, options, output_format)
+            .await?;
+
+        Ok((
+            [
+                (header::CONTENT_TYPE, mime.to_string()),
+                (header::CONTENT_LENGTH, bytes.len().to_string()),
+            ],
+            bytes,
+        )
+            .into_response())
+    }
+
     /// 处理图像（表单格式）
     pub async fn process_image_form(
+        State(controller): State<ImageController>,
         mut multipart: Multipart,
     ) -> Result<Json<CommonResp<ProcessImageData>>, AppError> {
         info!("收到表单图像处理请求");
 
-        let controller = Self::new();
         let mut image_data: Option<Vec<u8>> = None;
-        let mut output_format = "png".to_string();
+        let mut output_format: Option<String> = None;
+        let mut width: Option<u32> = None;
+        let mut height: Option<u32> = None;
+        let mut resize_filter: Option<String> = None;
+        let mut preserve_aspect = false;
+        let mut background: Option<String> = None;
+        let mut color_tolerance: Option<f32> = None;
+        let mut edge_blur: Option<u32> = None;
+        let mut morphology_iterations: Option<u32> = None;
 
         while let Some(field) = multipart
             .next_field()
@@ -95,8 +239,76 @@ impl ImageController {
                         .bytes()
                         .await
                         .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
-                    output_format =
-                        String::from_utf8(data.to_vec()).unwrap_or_else(|_| "png".to_string());
+                    output_format = String::from_utf8(data.to_vec()).ok();
+                }
+                "width" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    width = String::from_utf8(data.to_vec())
+                        .ok()
+                        .and_then(|s| s.parse().ok());
+                }
+                "height" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    height = String::from_utf8(data.to_vec())
+                        .ok()
+                        .and_then(|s| s.parse().ok());
+                }
+                "resizeFilter" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    resize_filter = String::from_utf8(data.to_vec()).ok();
+                }
+                "preserveAspect" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    preserve_aspect = matches!(
+                        String::from_utf8(data.to_vec()).ok().as_deref(),
+                        Some("true") | Some("1")
+                    );
+                }
+                "background" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    background = String::from_utf8(data.to_vec()).ok();
+                }
+                "colorTolerance" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    color_tolerance = String::from_utf8(data.to_vec())
+                        .ok()
+                        .and_then(|s| s.parse().ok());
+                }
+                "edgeBlur" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    edge_blur = String::from_utf8(data.to_vec())
+                        .ok()
+                        .and_then(|s| s.parse().ok());
+                }
+                "morphologyIterations" => {
+                    let data = field
+                        .bytes()
+                        .await
+                        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+                    morphology_iterations = String::from_utf8(data.to_vec())
+                        .ok()
+                        .and_then(|s| s.parse().ok());
                 }
                 _ => {}
             }
@@ -105,16 +317,27 @@ impl ImageController {
         let image_data =
             image_data.ok_or_else(|| AppError::InvalidInput("未找到图片文件".to_string()))?;
 
-        // 处理图像
-        let processed_image = controller.service.process_image_data(image_data).await?;
+        // 解析缩放参数
+        let resize = build_resize_options(width, height, resize_filter.as_deref(), preserve_aspect);
+
+        let (options, output_format) = build_options(
+            &controller.service,
+            resize,
+            background.as_deref(),
+            output_format.as_deref(),
+            color_tolerance,
+            edge_blur,
+            morphology_iterations,
+        )?;
 
-        // 转换为Base64
-        let base64_image = controller
+        // 处理图像并编码（命中缓存时跳过解码/去背）
+        let (bytes, _mime) = controller
             .service
-            .image_to_base64(processed_image, &output_format)?;
+            .process_and_encode(image_data, options, output_format.clone())
+            .await?;
 
         let response_data = ProcessImageData {
-            processed_image: base64_image,
+            processed_image: general_purpose::STANDARD.encode(&bytes),
             format: output_format,
         };
 
@@ -124,6 +347,6 @@ impl ImageController {
 
 impl Default for ImageController {
     fn default() -> Self {
-        Self::new()
+        Self::with_defaults(ServiceDefaults::default())
     }
 }
@@ -14,6 +14,23 @@ pub struct ProcessImageRequest {
     pub image_data: Option<String>,
     /// 输出格式，默认为PNG
     pub output_format: Option<String>,
+    /// 目标宽度（可选，与height配合进行缩放）
+    pub width: Option<u32>,
+    /// 目标高度（可选，与width配合进行缩放）
+    pub height: Option<u32>,
+    /// 缩放滤镜："nearest" | "triangle" | "catmull-rom" | "gaussian" | "lanczos3"，默认lanczos3
+    pub resize_filter: Option<String>,
+    /// 是否保持宽高比（在目标边界框内缩放，而非拉伸至精确尺寸）
+    pub preserve_aspect: Option<bool>,
+    /// 背景处理方式："transparent"（默认）、十六进制颜色（"#RRGGBB"/"#RRGGBBAA"）
+    /// 或两色渐变 "gradient:vertical|horizontal:#RRGGBB:#RRGGBB"
+    pub background: Option<String>,
+    /// 背景移除色彩容差，覆盖服务端默认值
+    pub color_tolerance: Option<f32>,
+    /// 背景移除边缘模糊程度，覆盖服务端默认值
+    pub edge_blur: Option<u32>,
+    /// 背景移除蒙版形态学开闭运算迭代次数，覆盖服务端默认值
+    pub morphology_iterations: Option<u32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -61,6 +78,8 @@ pub enum AppError {
     Base64Decode(#[from] base64::DecodeError),
     #[error("输入错误: {0}")]
     InvalidInput(String),
+    #[error("{0}")]
+    DimensionExceeded(String),
     #[error("内部服务器错误: {0}")]
     Internal(#[from] anyhow::Error),
 }
@@ -73,6 +92,7 @@ impl IntoResponse for AppError {
             AppError::InvalidInput(_) => StatusCode::BAD_REQUEST,
             AppError::ImageDownload(_) => StatusCode::BAD_REQUEST,
             AppError::Base64Decode(_) => StatusCode::BAD_REQUEST,
+            AppError::DimensionExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -1,23 +1,312 @@
+use std::{
+    io::Cursor,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use base64::{engine::general_purpose, Engine as _};
-use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use image::{imageops::FilterType, io::Limits, DynamicImage, ImageFormat, Rgba, RgbaImage};
+use lru::LruCache;
 use tracing::info;
 
 use crate::image_processor::model::AppError;
 
+/// 默认最大输入字节数（50 MB），超过该大小的请求在解码前直接拒绝
+const DEFAULT_MAX_INPUT_BYTES: u64 = 50 * 1024 * 1024;
+/// 默认最大图片宽/高（像素），防止解压缩炸弹占满内存
+const DEFAULT_MAX_DIMENSION: u32 = 10_000;
+/// 默认解码时允许分配的最大内存字节数（256 MB），防止宽高均合规但像素总量仍然巨大的图片耗尽内存
+const DEFAULT_MAX_ALLOC_BYTES: u64 = 256 * 1024 * 1024;
+/// 默认结果缓存容量（条目数）
+const DEFAULT_CACHE_CAPACITY: usize = 100;
+/// 默认缓存条目存活时间
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+/// 默认色彩容差
+const DEFAULT_COLOR_TOLERANCE: f32 = 30.0;
+/// 默认边缘模糊程度
+const DEFAULT_EDGE_BLUR: u32 = 2;
+/// 边缘模糊程度允许的最大值：`calculate_edge_alpha`单像素开销为O((2·edge_blur+1)²)，
+/// 不加上限时客户端可用一个极大值把单次请求拖成长时间CPU占用
+pub(crate) const MAX_EDGE_BLUR: u32 = 20;
+/// 默认形态学开闭运算迭代次数
+const DEFAULT_MORPHOLOGY_ITERATIONS: u32 = 1;
+/// 形态学开闭运算迭代次数允许的最大值：每次迭代都是一次O(width·height)的全图腐蚀/膨胀扫描，
+/// 不加上限时客户端可用一个极大值把单次请求拖成长时间CPU占用
+pub(crate) const MAX_MORPHOLOGY_ITERATIONS: u32 = 20;
+
+/// 服务的可配置默认值，由`AppConfig`在启动时构建，供`ImageProcessService::with_defaults`使用
+#[derive(Debug, Clone)]
+pub struct ServiceDefaults {
+    /// 解码前允许的最大输入字节数
+    pub max_input_bytes: u64,
+    /// 解码时允许的最大图片宽度（像素）
+    pub max_width: u32,
+    /// 解码时允许的最大图片高度（像素）
+    pub max_height: u32,
+    /// 解码时允许分配的最大内存字节数
+    pub max_alloc_bytes: u64,
+    /// 背景移除的默认色彩容差，可被单次请求覆盖
+    pub default_color_tolerance: f32,
+    /// 背景移除的默认边缘模糊程度，可被单次请求覆盖
+    pub default_edge_blur: u32,
+    /// 背景移除蒙版形态学开闭运算的默认迭代次数，可被单次请求覆盖
+    pub default_morphology_iterations: u32,
+}
+
+impl Default for ServiceDefaults {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            max_width: DEFAULT_MAX_DIMENSION,
+            max_height: DEFAULT_MAX_DIMENSION,
+            max_alloc_bytes: DEFAULT_MAX_ALLOC_BYTES,
+            default_color_tolerance: DEFAULT_COLOR_TOLERANCE,
+            default_edge_blur: DEFAULT_EDGE_BLUR,
+            default_morphology_iterations: DEFAULT_MORPHOLOGY_ITERATIONS,
+        }
+    }
+}
+
+/// 已编码的处理结果缓存项
+#[derive(Debug, Clone)]
+struct CachedResult {
+    bytes: Vec<u8>,
+    mime: &'static str,
+    cached_at: Instant,
+}
+
+/// 以原始输入字节+处理参数的哈希为键的结果缓存
+type ResultCache = Mutex<LruCache<String, CachedResult>>;
+
+/// 缩放参数
+#[derive(Debug, Clone, Default)]
+pub struct ResizeOptions {
+    /// 目标宽度，None时沿用原图宽度
+    pub width: Option<u32>,
+    /// 目标高度，None时沿用原图高度
+    pub height: Option<u32>,
+    /// 重采样滤镜，None时在需要缩放时默认使用Lanczos3
+    pub filter: Option<FilterType>,
+    /// 是否在边界框内保持宽高比缩放，而非拉伸至精确尺寸
+    pub preserve_aspect: bool,
+}
+
+/// 单次请求生效的完整处理参数
+#[derive(Debug, Clone)]
+pub struct ProcessOptions {
+    pub resize: ResizeOptions,
+    pub background: Background,
+    /// 背景移除色彩容差（已套用请求覆盖或服务默认值）
+    pub color_tolerance: f32,
+    /// 背景移除边缘模糊程度（已套用请求覆盖或服务默认值）
+    pub edge_blur: u32,
+    /// 背景移除蒙版形态学开闭运算迭代次数（已套用请求覆盖或服务默认值）
+    pub morphology_iterations: u32,
+}
+
+/// 将字符串映射为image库的重采样滤镜
+pub(crate) fn parse_filter_type(name: &str) -> FilterType {
+    match name.to_lowercase().as_str() {
+        "nearest" => FilterType::Nearest,
+        "triangle" => FilterType::Triangle,
+        "catmull-rom" | "catmullrom" => FilterType::CatmullRom,
+        "gaussian" => FilterType::Gaussian,
+        "lanczos3" => FilterType::Lanczos3,
+        _ => FilterType::Lanczos3,
+    }
+}
+
+/// 在两个通道值之间按比例t(0.0-1.0)线性插值
+fn lerp_channel(a: u8, b: u8, t: f32) -> u8 {
+    (a as f32 + (b as f32 - a as f32) * t).round() as u8
+}
+
+/// 按alpha(0.0-1.0)混合前景和背景通道值
+fn blend_channel(fg: u8, bg: u8, alpha: f32) -> u8 {
+    (fg as f32 * alpha + bg as f32 * (1.0 - alpha)).round() as u8
+}
+
+/// 渐变方向
+#[derive(Debug, Clone, Copy)]
+pub enum GradientDirection {
+    Vertical,
+    Horizontal,
+}
+
+/// 背景替换模式
+#[derive(Debug, Clone)]
+pub enum Background {
+    /// 保留透明通道（默认行为）
+    Transparent,
+    /// 合成到纯色背景上
+    Solid(Rgba<u8>),
+    /// 合成到两色渐变背景上
+    Gradient {
+        direction: GradientDirection,
+        start: Rgba<u8>,
+        end: Rgba<u8>,
+    },
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Transparent
+    }
+}
+
+impl Background {
+    /// 是否会产出不透明的结果（从而可以安全地编码为JPEG）
+    pub fn is_opaque(&self) -> bool {
+        !matches!(self, Background::Transparent)
+    }
+}
+
+/// 解析背景参数："transparent"、十六进制颜色（"#RRGGBB"/"#RRGGBBAA"）
+/// 或渐变 "gradient:vertical|horizontal:#RRGGBB:#RRGGBB"
+pub(crate) fn parse_background(spec: &str) -> Result<Background, AppError> {
+    let spec = spec.trim();
+    if spec.eq_ignore_ascii_case("transparent") {
+        return Ok(Background::Transparent);
+    }
+
+    if let Some(rest) = spec.strip_prefix("gradient:") {
+        let parts: Vec<&str> = rest.split(':').collect();
+        let [direction, start, end] = parts.as_slice() else {
+            return Err(AppError::InvalidInput(format!(
+                "无效的渐变背景格式: {spec}，应为 gradient:vertical|horizontal:#RRGGBB:#RRGGBB"
+            )));
+        };
+
+        let direction = match direction.to_lowercase().as_str() {
+            "vertical" => GradientDirection::Vertical,
+            "horizontal" => GradientDirection::Horizontal,
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "无效的渐变方向: {direction}，应为vertical或horizontal"
+                )))
+            }
+        };
+
+        return Ok(Background::Gradient {
+            direction,
+            start: parse_hex_color(start)?,
+            end: parse_hex_color(end)?,
+        });
+    }
+
+    Ok(Background::Solid(parse_hex_color(spec)?))
+}
+
+/// 解析十六进制颜色 "#RRGGBB" 或 "#RRGGBBAA"
+fn parse_hex_color(hex: &str) -> Result<Rgba<u8>, AppError> {
+    let hex = hex.trim().trim_start_matches('#');
+    let parse_channel = |s: &str| {
+        u8::from_str_radix(s, 16)
+            .map_err(|_| AppError::InvalidInput(format!("无效的十六进制颜色: #{hex}")))
+    };
+
+    match hex.len() {
+        6 => Ok(Rgba([
+            parse_channel(&hex[0..2])?,
+            parse_channel(&hex[2..4])?,
+            parse_channel(&hex[4..6])?,
+            255,
+        ])),
+        8 => Ok(Rgba([
+            parse_channel(&hex[0..2])?,
+            parse_channel(&hex[2..4])?,
+            parse_channel(&hex[4..6])?,
+            parse_channel(&hex[6..8])?,
+        ])),
+        _ => Err(AppError::InvalidInput(format!(
+            "无效的十六进制颜色: #{hex}"
+        ))),
+    }
+}
+
 /// 图片处理服务
-pub struct ImageProcessService;
+pub struct ImageProcessService {
+    /// 解码前允许的最大输入字节数
+    max_input_bytes: u64,
+    /// 解码时允许的最大图片宽度（像素）
+    max_width: u32,
+    /// 解码时允许的最大图片高度（像素）
+    max_height: u32,
+    /// 解码时允许分配的最大内存字节数
+    max_alloc_bytes: u64,
+    /// 背景移除的默认色彩容差，单次请求可覆盖
+    default_color_tolerance: f32,
+    /// 背景移除的默认边缘模糊程度，单次请求可覆盖
+    default_edge_blur: u32,
+    /// 背景移除蒙版形态学开闭运算的默认迭代次数，单次请求可覆盖
+    default_morphology_iterations: u32,
+    /// 已编码结果缓存，键为输入字节+处理参数的哈希
+    cache: Arc<ResultCache>,
+    /// 缓存条目存活时间，超过后视为未命中
+    cache_ttl: Duration,
+}
 
 impl ImageProcessService {
-    pub fn new() -> Self {
-        Self
+    /// 使用启动时配置的默认值创建服务
+    pub fn with_defaults(defaults: ServiceDefaults) -> Self {
+        Self {
+            max_input_bytes: defaults.max_input_bytes,
+            max_width: defaults.max_width,
+            max_height: defaults.max_height,
+            max_alloc_bytes: defaults.max_alloc_bytes,
+            default_color_tolerance: defaults.default_color_tolerance,
+            default_edge_blur: defaults.default_edge_blur,
+            default_morphology_iterations: defaults.default_morphology_iterations,
+            cache: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(DEFAULT_CACHE_CAPACITY).unwrap(),
+            ))),
+            cache_ttl: DEFAULT_CACHE_TTL,
+        }
+    }
+
+    /// 背景移除的默认色彩容差，供调用方在请求未覆盖时使用
+    pub fn default_color_tolerance(&self) -> f32 {
+        self.default_color_tolerance
+    }
+
+    /// 背景移除的默认边缘模糊程度，供调用方在请求未覆盖时使用
+    pub fn default_edge_blur(&self) -> u32 {
+        self.default_edge_blur
     }
 
-    /// 下载图片
+    /// 背景移除蒙版形态学开闭运算的默认迭代次数，供调用方在请求未覆盖时使用
+    pub fn default_morphology_iterations(&self) -> u32 {
+        self.default_morphology_iterations
+    }
+
+    /// 下载图片。按块读取响应体并在读取过程中持续核对`max_input_bytes`，
+    /// 避免在检查大小前就把一个巨大的响应体整个缓冲进内存
     pub async fn download_image(&self, url: &str) -> Result<Vec<u8>, AppError> {
         info!("从URL下载图片: {}", url);
-        let response = reqwest::get(url).await?;
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
+        let mut response = reqwest::get(url).await?;
+
+        if let Some(content_length) = response.content_length() {
+            if content_length > self.max_input_bytes {
+                return Err(AppError::DimensionExceeded(format!(
+                    "图片URL响应体大小{}字节超出限制{}字节",
+                    content_length, self.max_input_bytes
+                )));
+            }
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() as u64 > self.max_input_bytes {
+                return Err(AppError::DimensionExceeded(format!(
+                    "图片URL响应体大小超出限制{}字节",
+                    self.max_input_bytes
+                )));
+            }
+        }
+
+        Ok(bytes)
     }
 
     /// 解码Base64图片数据
@@ -27,40 +316,271 @@ impl ImageProcessService {
     }
 
     /// 处理图像数据
-    pub async fn process_image_data(&self, image_data: Vec<u8>) -> Result<DynamicImage, AppError> {
+    pub async fn process_image_data(
+        &self,
+        image_data: Vec<u8>,
+        options: ProcessOptions,
+    ) -> Result<DynamicImage, AppError> {
+        // 解码前先检查原始字节大小，超出max_input_bytes直接拒绝；
+        // 体积小但解码后尺寸巨大的图片由下方解码时的Limits拦截
+        if image_data.len() as u64 > self.max_input_bytes {
+            return Err(AppError::DimensionExceeded(format!(
+                "输入图片大小{}字节超出限制{}字节",
+                image_data.len(),
+                self.max_input_bytes
+            )));
+        }
+
+        let max_width = self.max_width;
+        let max_height = self.max_height;
+        let max_alloc_bytes = self.max_alloc_bytes;
+
         // 在异步上下文中处理图像
         tokio::task::spawn_blocking(move || -> Result<DynamicImage, AppError> {
-            // 解码图像
-            let mut image = image::load_from_memory(&image_data)?;
+            // 解码图像，附加尺寸/内存限制防止解压缩炸弹
+            let mut limits = Limits::default();
+            limits.max_image_width = Some(max_width);
+            limits.max_image_height = Some(max_height);
+            limits.max_alloc = Some(max_alloc_bytes);
+
+            let mut reader = image::io::Reader::new(Cursor::new(&image_data))
+                .with_guessed_format()
+                .map_err(image::ImageError::IoError)?;
+            reader.limits(limits);
+
+            let mut image = reader.decode().map_err(|e| match e {
+                image::ImageError::Limits(_) => AppError::DimensionExceeded(format!(
+                    "图片尺寸或解码所需内存超出限制（最大{}x{}像素，最大{}字节）",
+                    max_width, max_height, max_alloc_bytes
+                )),
+                other => AppError::from(other),
+            })?;
 
             // 移除背景
-            let bg_remover = BackgroundRemover::new();
+            let bg_remover = BackgroundRemover::new(options.color_tolerance, options.edge_blur)
+                .with_morphology_iterations(options.morphology_iterations);
             image = bg_remover.remove_background(image)?;
 
+            // 按需合成到新背景（纯色/渐变），或保留透明通道
+            image = Self::composite_background(image, &options.background);
+
+            // 缩放/生成缩略图，目标尺寸同样受解码尺寸限制约束，防止绕过限流直接请求超大输出
+            image = Self::resize_image(image, &options.resize, max_width, max_height)?;
+
             Ok(image)
         })
         .await
         .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?
     }
 
-    /// 将图像转换为指定格式的Base64字符串
-    pub fn image_to_base64(&self, image: DynamicImage, format: &str) -> Result<String, AppError> {
-        let mut output_bytes = Vec::new();
-        let image_format = match format {
-            "png" => ImageFormat::Png,
-            "jpg" | "jpeg" => ImageFormat::Jpeg,
-            "webp" => ImageFormat::WebP,
-            _ => ImageFormat::Png,
+    /// 根据缩放参数调整图像尺寸，width和height均未设置时原样返回。
+    /// 目标尺寸超出解码限制时直接返回`DimensionExceeded`，避免客户端通过请求超大输出尺寸绕过`max_width`/`max_height`
+    fn resize_image(
+        image: DynamicImage,
+        resize: &ResizeOptions,
+        max_width: u32,
+        max_height: u32,
+    ) -> Result<DynamicImage, AppError> {
+        if resize.width.is_none() && resize.height.is_none() {
+            return Ok(image);
+        }
+
+        let target_width = resize.width.unwrap_or_else(|| image.width());
+        let target_height = resize.height.unwrap_or_else(|| image.height());
+
+        if target_width > max_width || target_height > max_height {
+            return Err(AppError::DimensionExceeded(format!(
+                "目标缩放尺寸{target_width}x{target_height}超出限制（最大{max_width}x{max_height}像素）"
+            )));
+        }
+
+        let filter = resize.filter.unwrap_or(FilterType::Lanczos3);
+        Ok(if resize.preserve_aspect {
+            image.resize(target_width, target_height, filter)
+        } else {
+            image.resize_exact(target_width, target_height, filter)
+        })
+    }
+
+    /// 按背景选项合成图像：透明保持不变，纯色/渐变则alpha混合到不透明背景上
+    fn composite_background(image: DynamicImage, background: &Background) -> DynamicImage {
+        let foreground = match &background {
+            Background::Transparent => return image,
+            _ => image.to_rgba8(),
+        };
+        let (width, height) = foreground.dimensions();
+
+        let backdrop = match background {
+            Background::Transparent => unreachable!(),
+            Background::Solid(color) => RgbaImage::from_pixel(width, height, *color),
+            Background::Gradient {
+                direction,
+                start,
+                end,
+            } => Self::generate_gradient(width, height, *direction, *start, *end),
         };
 
-        image.write_to(&mut std::io::Cursor::new(&mut output_bytes), image_format)?;
+        Self::alpha_composite(&foreground, &backdrop)
+    }
+
+    /// 生成两色渐变背景
+    fn generate_gradient(
+        width: u32,
+        height: u32,
+        direction: GradientDirection,
+        start: Rgba<u8>,
+        end: Rgba<u8>,
+    ) -> RgbaImage {
+        let mut gradient = RgbaImage::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let t = match direction {
+                    GradientDirection::Vertical => {
+                        if height > 1 {
+                            y as f32 / (height - 1) as f32
+                        } else {
+                            0.0
+                        }
+                    }
+                    GradientDirection::Horizontal => {
+                        if width > 1 {
+                            x as f32 / (width - 1) as f32
+                        } else {
+                            0.0
+                        }
+                    }
+                };
+
+                gradient.put_pixel(
+                    x,
+                    y,
+                    Rgba([
+                        lerp_channel(start[0], end[0], t),
+                        lerp_channel(start[1], end[1], t),
+                        lerp_channel(start[2], end[2], t),
+                        255,
+                    ]),
+                );
+            }
+        }
+
+        gradient
+    }
+
+    /// 按alpha通道将前景混合到背景上：out = fg*a + bg*(1-a)，结果不透明
+    fn alpha_composite(foreground: &RgbaImage, backdrop: &RgbaImage) -> DynamicImage {
+        let (width, height) = foreground.dimensions();
+        let mut result = RgbaImage::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let fg = foreground.get_pixel(x, y);
+                let bg = backdrop.get_pixel(x, y);
+                let alpha = fg[3] as f32 / 255.0;
+
+                result.put_pixel(
+                    x,
+                    y,
+                    Rgba([
+                        blend_channel(fg[0], bg[0], alpha),
+                        blend_channel(fg[1], bg[1], alpha),
+                        blend_channel(fg[2], bg[2], alpha),
+                        255,
+                    ]),
+                );
+            }
+        }
+
+        DynamicImage::ImageRgba8(result)
+    }
+
+    /// 将图像编码为指定格式的原始字节，并返回对应的MIME类型
+    pub fn image_to_bytes(
+        &self,
+        image: DynamicImage,
+        format: &str,
+    ) -> Result<(Vec<u8>, &'static str), AppError> {
+        let (image_format, mime) = match format {
+            "jpg" | "jpeg" => (ImageFormat::Jpeg, "image/jpeg"),
+            "webp" => (ImageFormat::WebP, "image/webp"),
+            _ => (ImageFormat::Png, "image/png"),
+        };
+
+        let mut output_bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut output_bytes), image_format)?;
+        Ok((output_bytes, mime))
+    }
+
+    /// 将图像转换为指定格式的Base64字符串
+    pub fn image_to_base64(&self, image: DynamicImage, format: &str) -> Result<String, AppError> {
+        let (output_bytes, _mime) = self.image_to_bytes(image, format)?;
         Ok(general_purpose::STANDARD.encode(&output_bytes))
     }
+
+    /// 处理并编码图像，命中缓存时直接返回已编码字节，避免重复解码/去背
+    pub async fn process_and_encode(
+        &self,
+        image_data: Vec<u8>,
+        options: ProcessOptions,
+        format: String,
+    ) -> Result<(Vec<u8>, &'static str), AppError> {
+        let key = Self::cache_key(&image_data, &options, &format);
+
+        if let Some(cached) = self.cache_get(&key) {
+            return Ok((cached.bytes, cached.mime));
+        }
+
+        let processed_image = self.process_image_data(image_data, options).await?;
+        let (bytes, mime) = self.image_to_bytes(processed_image, &format)?;
+
+        self.cache_put(
+            key,
+            CachedResult {
+                bytes: bytes.clone(),
+                mime,
+                cached_at: Instant::now(),
+            },
+        );
+
+        Ok((bytes, mime))
+    }
+
+    /// 对原始输入字节及生效的处理参数取哈希，作为缓存键
+    fn cache_key(image_data: &[u8], options: &ProcessOptions, format: &str) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(image_data);
+        hasher.update(format.as_bytes());
+        hasher.update(format!("{:?}", options.resize).as_bytes());
+        hasher.update(format!("{:?}", options.background).as_bytes());
+        hasher.update(&options.color_tolerance.to_le_bytes());
+        hasher.update(&options.edge_blur.to_le_bytes());
+        hasher.update(&options.morphology_iterations.to_le_bytes());
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// 查询缓存，过期或不存在则返回None
+    fn cache_get(&self, key: &str) -> Option<CachedResult> {
+        let mut cache = self.cache.lock().unwrap();
+        match cache.get(key) {
+            Some(entry) if entry.cached_at.elapsed() <= self.cache_ttl => Some(entry.clone()),
+            Some(_) => {
+                cache.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 写入缓存
+    fn cache_put(&self, key: String, value: CachedResult) {
+        self.cache.lock().unwrap().put(key, value);
+    }
 }
 
 impl Default for ImageProcessService {
     fn default() -> Self {
-        Self::new()
+        Self::with_defaults(ServiceDefaults::default())
     }
 }
 
@@ -70,17 +590,26 @@ pub struct BackgroundRemover {
     color_tolerance: f32,
     /// 边缘模糊程度
     edge_blur: u32,
+    /// 蒙版形态学开闭运算的迭代次数
+    morphology_iterations: u32,
 }
 
 impl BackgroundRemover {
-    /// 创建新的背景移除器
-    pub fn new() -> Self {
+    /// 创建新的背景移除器，形态学迭代次数使用默认值
+    pub fn new(color_tolerance: f32, edge_blur: u32) -> Self {
         Self {
-            color_tolerance: 30.0,
-            edge_blur: 2,
+            color_tolerance,
+            edge_blur,
+            morphology_iterations: DEFAULT_MORPHOLOGY_ITERATIONS,
         }
     }
 
+    /// 设置蒙版形态学开闭运算的迭代次数
+    pub fn with_morphology_iterations(mut self, morphology_iterations: u32) -> Self {
+        self.morphology_iterations = morphology_iterations;
+        self
+    }
+
     /// 移除背景
     pub fn remove_background(
         &self,
@@ -161,12 +690,82 @@ impl BackgroundRemover {
             }
         }
 
-        // 使用泛洪填充去除连接的背景区域
+        // 使用泛洪填充去除连接的背景区域（仅移除从边缘可达的背景，被主体包围的同色区域保留为前景）
         self.flood_fill_background(&mut mask, width as usize, height as usize);
 
+        // 开运算（先腐蚀后膨胀）清除贴着主体边缘的孤立背景噪点，
+        // 闭运算（先膨胀后腐蚀）填补主体内部的单像素孔洞。两者在泛洪填充之后执行，会改动其结果：
+        // 开运算会整体抹去容不下3x3结构元的细小前景，闭运算会填平单像素宽的背景缝隙
+        for _ in 0..self.morphology_iterations {
+            mask = Self::erode(&mask, width as usize, height as usize);
+            mask = Self::dilate(&mask, width as usize, height as usize);
+        }
+        for _ in 0..self.morphology_iterations {
+            mask = Self::dilate(&mask, width as usize, height as usize);
+            mask = Self::erode(&mask, width as usize, height as usize);
+        }
+
         mask
     }
 
+    /// 腐蚀：3x3结构元，仅当像素及其全部8邻域都为前景时才保留为前景（画面外视为背景）
+    fn erode(mask: &[Vec<bool>], width: usize, height: usize) -> Vec<Vec<bool>> {
+        let mut result = mask.to_vec();
+
+        for y in 0..height {
+            for x in 0..width {
+                if !mask[y][x] {
+                    continue;
+                }
+
+                let mut all_foreground = true;
+                for dy in -1..=1i32 {
+                    for dx in -1..=1i32 {
+                        let nx = x as i32 + dx;
+                        let ny = y as i32 + dy;
+                        let in_bounds =
+                            nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32;
+                        if !in_bounds || !mask[ny as usize][nx as usize] {
+                            all_foreground = false;
+                        }
+                    }
+                }
+                result[y][x] = all_foreground;
+            }
+        }
+
+        result
+    }
+
+    /// 膨胀：3x3结构元，只要像素的8邻域中存在前景即标记为前景
+    fn dilate(mask: &[Vec<bool>], width: usize, height: usize) -> Vec<Vec<bool>> {
+        let mut result = mask.to_vec();
+
+        for y in 0..height {
+            for x in 0..width {
+                if mask[y][x] {
+                    continue;
+                }
+
+                let mut any_foreground = false;
+                for dy in -1..=1i32 {
+                    for dx in -1..=1i32 {
+                        let nx = x as i32 + dx;
+                        let ny = y as i32 + dy;
+                        let in_bounds =
+                            nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32;
+                        if in_bounds && mask[ny as usize][nx as usize] {
+                            any_foreground = true;
+                        }
+                    }
+                }
+                result[y][x] = any_foreground;
+            }
+        }
+
+        result
+    }
+
     /// 泛洪填充背景
     fn flood_fill_background(&self, mask: &mut [Vec<bool>], width: usize, height: usize) {
         let mut visited = vec![vec![false; width]; height];
@@ -189,6 +788,16 @@ impl BackgroundRemover {
                 self.flood_fill_iterative(mask, &mut visited, x, y, width, height);
             }
         }
+
+        // 颜色匹配但未被泛洪填充触达的区域，说明被主体完全包围，与边缘不连通，
+        // 应恢复为前景（例如主体内部恰好与背景同色的"孔洞"）
+        for y in 0..height {
+            for x in 0..width {
+                if !mask[y][x] && !visited[y][x] {
+                    mask[y][x] = true;
+                }
+            }
+        }
     }
 
     /// 迭代泛洪填充（避免栈溢出）
@@ -300,18 +909,128 @@ impl BackgroundRemover {
         (1.0 - background_ratio).max(0.0)
     }
 
-    /// 计算两个颜色之间的距离
+    /// 计算两个颜色之间的感知加权距离，比原始欧氏距离更贴近人眼对亮度的敏感度
     fn color_distance(&self, color1: &Rgba<u8>, color2: &Rgba<u8>) -> f32 {
         let dr = color1[0] as f32 - color2[0] as f32;
         let dg = color1[1] as f32 - color2[1] as f32;
         let db = color1[2] as f32 - color2[2] as f32;
 
-        (dr * dr + dg * dg + db * db).sqrt()
+        (2.0 * dr * dr + 4.0 * dg * dg + 3.0 * db * db).sqrt()
     }
 }
 
 impl Default for BackgroundRemover {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_COLOR_TOLERANCE, DEFAULT_EDGE_BLUR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 生成一张纯色PNG图片的字节，供解码限制测试使用
+    fn encode_png(width: u32, height: u32, color: Rgba<u8>) -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, color));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn process_image_data_rejects_oversized_input_bytes() {
+        let png_bytes = encode_png(4, 4, Rgba([255, 0, 0, 255]));
+        let mut defaults = ServiceDefaults::default();
+        defaults.max_input_bytes = (png_bytes.len() - 1) as u64;
+        let service = ImageProcessService::with_defaults(defaults);
+
+        let result = service
+            .process_image_data(png_bytes, ProcessOptions {
+                resize: ResizeOptions::default(),
+                background: Background::default(),
+                color_tolerance: DEFAULT_COLOR_TOLERANCE,
+                edge_blur: DEFAULT_EDGE_BLUR,
+                morphology_iterations: DEFAULT_MORPHOLOGY_ITERATIONS,
+            })
+            .await;
+
+        assert!(matches!(result, Err(AppError::DimensionExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn process_image_data_rejects_oversized_dimensions() {
+        let png_bytes = encode_png(8, 8, Rgba([255, 0, 0, 255]));
+        let mut defaults = ServiceDefaults::default();
+        defaults.max_width = 4;
+        defaults.max_height = 4;
+        let service = ImageProcessService::with_defaults(defaults);
+
+        let result = service
+            .process_image_data(png_bytes, ProcessOptions {
+                resize: ResizeOptions::default(),
+                background: Background::default(),
+                color_tolerance: DEFAULT_COLOR_TOLERANCE,
+                edge_blur: DEFAULT_EDGE_BLUR,
+                morphology_iterations: DEFAULT_MORPHOLOGY_ITERATIONS,
+            })
+            .await;
+
+        assert!(matches!(result, Err(AppError::DimensionExceeded(_))));
+    }
+
+    #[test]
+    fn flood_fill_background_retains_hole_enclosed_by_foreground() {
+        let remover = BackgroundRemover::default();
+        let (width, height) = (5, 5);
+        let mut mask = vec![vec![true; width]; height];
+
+        // 外边缘一圈标记为背景色匹配，内部被前景环包围的一个像素也标记为背景色匹配
+        for x in 0..width {
+            mask[0][x] = false;
+            mask[height - 1][x] = false;
+        }
+        for row in mask.iter_mut() {
+            row[0] = false;
+            row[width - 1] = false;
+        }
+        mask[2][2] = false;
+
+        remover.flood_fill_background(&mut mask, width, height);
+
+        assert!(
+            mask[2][2],
+            "被前景完全包围、与边缘不连通的同色孔洞应恢复为前景"
+        );
+        assert!(!mask[0][0], "与边缘连通的背景区域应保持为背景");
+    }
+
+    #[test]
+    fn create_mask_retains_color_matched_hole_enclosed_by_subject() {
+        let white = Rgba([255, 255, 255, 255]);
+        let red = Rgba([255, 0, 0, 255]);
+        let rows = ["WWWWW", "WRRRW", "WRWRW", "WRRRW", "WWWWW"];
+        let height = rows.len() as u32;
+        let width = rows[0].len() as u32;
+
+        let mut image = RgbaImage::new(width, height);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let color = if ch == 'W' { white } else { red };
+                image.put_pixel(x as u32, y as u32, color);
+            }
+        }
+
+        // 关闭形态学开闭运算，单独验证泛洪填充对孔洞的处理
+        let remover = BackgroundRemover::new(0.0, 0).with_morphology_iterations(0);
+        let mask = remover.create_mask(&image, white);
+
+        assert!(
+            mask[2][2],
+            "主体内部与背景同色但被完全包围的像素应保留为前景"
+        );
+        assert!(!mask[0][0], "与边缘连通的背景像素应被判定为背景");
+        assert!(mask[1][1], "主体自身颜色的像素应保留为前景");
     }
 }
@@ -1,11 +1,16 @@
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post},
     Json, Router,
 };
+use clap::Parser;
+use tower::limit::ConcurrencyLimitLayer;
 use tower_http::cors::CorsLayer;
 use tracing::info;
 
+mod config;
 mod image_processor;
+use config::AppConfig;
 use image_processor::{controller::ImageController, model::CommonResp};
 
 #[tokio::main]
@@ -13,18 +18,32 @@ async fn main() {
     // 初始化日志
     tracing_subscriber::fmt::init();
 
+    // 解析命令行/环境变量配置
+    let config = AppConfig::parse();
+
+    // 共享的图片处理控制器（含结果缓存），所有请求复用同一实例
+    let controller = ImageController::with_defaults(config.service_defaults());
+
     // 创建路由
     let app = Router::new()
         .route("/", get(health_check))
         .route("/health", get(health_check))
         .route("/process", post(ImageController::process_image))
+        .route("/process-raw", post(ImageController::process_image_raw))
         .route("/process-form", post(ImageController::process_image_form))
-        .layer(CorsLayer::permissive());
+        .layer(CorsLayer::permissive())
+        .layer(ConcurrencyLimitLayer::new(config.max_concurrency))
+        // axum默认body大小限制为2MB，这里改为与解码前的max_input_bytes检查保持一致，
+        // 否则超出2MB但未超出max_input_bytes的请求会先被axum拒绝，配置的限制形同虚设
+        .layer(DefaultBodyLimit::max(config.max_input_bytes as usize))
+        .with_state(controller);
 
-    info!("启动图像处理服务在端口 3000");
+    info!("启动图像处理服务在 {}", config.listen_addr());
 
     // 启动服务器
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(config.listen_addr())
+        .await
+        .unwrap();
     axum::serve(listener, app).await.unwrap();
 }
 